@@ -12,6 +12,8 @@ use super::{
     Transpose, TransposeMain,
     erased_input_source_collection::{ErasedInputSource, ErasedInputSourceCollection},
     input_source_collection::InputSourceCollection,
+    retention_policy::RetentionPolicy,
+    rollback_horizon::RollbackHorizon,
     transpose_interrupt_waker::TransposeWakerObserver,
     working_timeline_slice::WorkingTimelineSlice,
 };
@@ -22,6 +24,8 @@ pub struct TransposeBuilder<T: Transposer + 'static> {
     input_sources: HashSet<ErasedInputSource<T>>,
     rng_seed: [u8; 32],
     max_channels: NonZeroUsize,
+    retention_policy: RetentionPolicy<T>,
+    rollback_horizon: Option<RollbackHorizon<T>>,
 }
 
 impl<T: Transposer + Clone + 'static> TransposeBuilder<T> {
@@ -33,9 +37,27 @@ impl<T: Transposer + Clone + 'static> TransposeBuilder<T> {
             input_sources: HashSet::new(),
             rng_seed,
             max_channels,
+            retention_policy: RetentionPolicy::KeepAll,
+            rollback_horizon: None,
         }
     }
 
+    /// Limit how far back input interrupts may roll back the transposer.
+    ///
+    /// Returns the self for chaining.
+    pub fn with_rollback_horizon(mut self, rollback_horizon: RollbackHorizon<T>) -> Self {
+        self.rollback_horizon = Some(rollback_horizon);
+        self
+    }
+
+    /// Set the policy for which saturated steps are retained as snapshots.
+    ///
+    /// Returns the self for chaining.
+    pub fn with_retention_policy(mut self, retention_policy: RetentionPolicy<T>) -> Self {
+        self.retention_policy = retention_policy;
+        self
+    }
+
     /// Assign an input source.
     ///
     /// Returns the self for chaining.
@@ -100,10 +122,13 @@ impl<T: Transposer + Clone + 'static> TransposeBuilder<T> {
             rng_seed,
             input_sources,
             max_channels: _,
+            retention_policy,
+            rollback_horizon,
         } = self;
 
         let working_timeline_slice =
-            WorkingTimelineSlice::new(transposer, pre_init_step, rng_seed).map_err(|_| ())?;
+            WorkingTimelineSlice::new(transposer, pre_init_step, rng_seed, retention_policy)
+                .map_err(|_| ())?;
 
         let input_sources = ErasedInputSourceCollection::new(input_sources)?;
         let wakers = TransposeWakerObserver::new(input_sources.iter_with_hashes().map(|(h, ..)| h));
@@ -113,6 +138,7 @@ impl<T: Transposer + Clone + 'static> TransposeBuilder<T> {
             main: TransposeMain {
                 input_sources,
                 working_timeline_slice,
+                rollback_horizon,
                 // interpolations: HashMap::new(),
                 // next_interpolation_uuid: 0,
                 // channel_reservations: InputChannelReservations::new(),
@@ -0,0 +1,23 @@
+/// A running summary of the work a [`Transpose`](super::Transpose) has done, for profiling and debug HUDs.
+///
+/// Individual occurrences are also emitted as `tracing` events, so a subscriber can be used for more detail.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransposeMetrics {
+    /// The number of interrupts which invalidated already computed steps.
+    pub rollbacks: usize,
+
+    /// The number of steps discarded by rollbacks.
+    pub steps_rolled_back: usize,
+
+    /// The number of times a step began saturating.
+    pub steps_saturated: usize,
+
+    /// The number of times a step began saturating again after the retention policy desaturated it.
+    pub steps_resaturated: usize,
+
+    /// The number of saturated steps desaturated by the retention policy.
+    pub steps_desaturated: usize,
+
+    /// The number of output events emitted by the steps.
+    pub events_emitted: usize,
+}
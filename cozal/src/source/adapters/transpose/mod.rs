@@ -1,5 +1,6 @@
 // #![allow(dead_code)]
 
+use archery::ArcTK;
 use input_source_collection::{AggregateSourcePoll, InputSourceCollection};
 use std::future::Future;
 use std::num::NonZeroUsize;
@@ -14,6 +15,10 @@ mod erased_input_source_collection;
 mod input_channel_reservations;
 mod input_source_collection;
 mod input_source_metadata;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod retention_policy;
+mod rollback_horizon;
 mod transpose_interrupt_waker;
 mod working_timeline_slice;
 
@@ -21,12 +26,16 @@ mod working_timeline_slice;
 mod test;
 
 pub use builder::TransposeBuilder;
+#[cfg(feature = "metrics")]
+pub use metrics::TransposeMetrics;
+pub use retention_policy::RetentionPolicy;
+pub use rollback_horizon::{LateInputMetrics, LateInputPolicy, RollbackHorizon};
 
-use crate::source::source_poll::{Interrupt, LowerBound, TrySourcePoll, UpperBound};
+use crate::source::source_poll::{Interrupt, LowerBound, SourcePollErr, TrySourcePoll, UpperBound};
 use crate::source::traits::SourceContext;
 use crate::source::{Source, SourcePoll};
 use crate::transposer::Transposer;
-use crate::transposer::step::PossiblyInitStep;
+use crate::transposer::step::{BoxedInput, PossiblyInitStep};
 
 pub struct Transpose<T: Transposer + 'static> {
     // most of the fields
@@ -53,6 +62,9 @@ struct TransposeMain<T: Transposer + 'static> {
 
     // the working steps and buffered inputs
     pub working_timeline_slice: WorkingTimelineSlice<T>,
+
+    // the limit on how far back inputs may roll back the working steps
+    pub rollback_horizon: Option<RollbackHorizon<T>>,
     // // uuid -> (forget, interpolation)
     // interpolations: HashMap<u64, (bool, Pin<Box<Interpolation<T, ArcTK>>>)>,
 
@@ -73,6 +85,76 @@ struct TransposeMain<T: Transposer + 'static> {
     // returned_state_times: BTreeSet<T::Time>,
 }
 
+impl<T: Transposer + 'static> Transpose<T> {
+    /// The counts of input interrupts which arrived older than the rollback horizon.
+    ///
+    /// This is all zeroes if no rollback horizon was configured.
+    pub fn late_input_metrics(&self) -> LateInputMetrics {
+        self.main
+            .rollback_horizon
+            .as_ref()
+            .map(RollbackHorizon::metrics)
+            .unwrap_or_default()
+    }
+
+    /// A summary of the rollbacks and step saturation done so far.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> TransposeMetrics {
+        self.main.working_timeline_slice.metrics()
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T> Transpose<T>
+where
+    T: Transposer + Clone + Send + Sync + 'static,
+    T::Time: Send + Sync,
+    T::Scheduled: Send + Sync,
+{
+    /// Recompute the snapshots needed for the states at each of `times` at once, on the rayon thread pool.
+    ///
+    /// Steps the retention policy discarded are normally recomputed one at a time from the nearest snapshot,
+    /// when a state needs them. After a rollback or a seek, a caller which is about to poll states at several
+    /// earlier times can call this first, so the runs of steps starting from different snapshots are recomputed
+    /// in parallel.
+    ///
+    /// Only steps which handle nothing but scheduled events are recomputed this way. The rest are left to `poll`.
+    /// Snapshots of the transposer are shared with the thread pool, so its state must be `Send` and `Sync`, but
+    /// the futures of its handlers never leave the thread which created them.
+    pub fn resaturate_in_parallel(&mut self, times: impl IntoIterator<Item = T::Time>) {
+        self.main
+            .working_timeline_slice
+            .resaturate_in_parallel(times);
+    }
+}
+
+impl<T: Transposer + Clone + 'static> TransposeMain<T> {
+    /// apply the rollback horizon to an interrupt from an input, then hand it to the working timeline slice.
+    ///
+    /// returns the time events must be rolled back to, if any emitted events were invalidated.
+    fn handle_input_interrupt(
+        &mut self,
+        input_hash: u64,
+        time: T::Time,
+        interrupt: Interrupt<BoxedInput<'static, T, ArcTK>>,
+    ) -> Result<Option<T::Time>, SourcePollErr> {
+        let (time, interrupt) = match &mut self.rollback_horizon {
+            Some(rollback_horizon) => {
+                let frontier = self.working_timeline_slice.frontier_time();
+                match rollback_horizon.screen(frontier, time, interrupt)? {
+                    Some(screened) => screened,
+                    None => return Ok(None),
+                }
+            }
+            None => (time, interrupt),
+        };
+
+        Ok(self
+            .working_timeline_slice
+            .handle_interrupt(input_hash, time, interrupt))
+    }
+}
+
 impl<T: Transposer + Clone + 'static> TransposeLocked<'_, T> {
     fn from_transpose(transpose: &mut Transpose<T>) -> TransposeLocked<'_, T> {
         let wakers = transpose.wakers.lock();
@@ -113,6 +195,9 @@ impl<T: Transposer + Clone + 'static> Source for Transpose<T> {
         &mut self,
         interrupt_waker: Waker,
     ) -> TrySourcePoll<Self::Time, Self::Event, ()> {
+        #[cfg(feature = "metrics")]
+        let _span = tracing::trace_span!("transpose_poll_interrupts").entered();
+
         let mut locked = TransposeLocked::from_transpose(self);
         locked.wakers.interrupt_waker = interrupt_waker;
 
@@ -147,8 +232,7 @@ impl<T: Transposer + Clone + 'static> Source for Transpose<T> {
                         .advance_interrupt_lower_bound(interrupt_lower_bound);
                     if let Some(time) = locked
                         .main
-                        .working_timeline_slice
-                        .handle_interrupt(input_hash, time, interrupt)
+                        .handle_input_interrupt(input_hash, time, interrupt)?
                     {
                         let interrupt_lower_bound = interrupt_lower_bound.min(
                             locked
@@ -156,6 +240,9 @@ impl<T: Transposer + Clone + 'static> Source for Transpose<T> {
                                 .working_timeline_slice
                                 .tentative_state_and_event_lower_bound(),
                         );
+                        #[cfg(feature = "metrics")]
+                        tracing::debug!(?time, "transpose rolling back");
+
                         return TrySourcePoll::Ok(SourcePoll::Interrupt {
                             time,
                             interrupt: Interrupt::Rollback,
@@ -222,8 +309,7 @@ impl<T: Transposer + Clone + 'static> Source for Transpose<T> {
                                 .advance_interrupt_lower_bound(interrupt_lower_bound);
                             if let Some(time) = locked
                                 .main
-                                .working_timeline_slice
-                                .handle_interrupt(input_hash, time, interrupt)
+                                .handle_input_interrupt(input_hash, time, interrupt)?
                             {
                                 let interrupt_lower_bound = interrupt_lower_bound.min(
                                     locked
@@ -231,6 +317,9 @@ impl<T: Transposer + Clone + 'static> Source for Transpose<T> {
                                         .working_timeline_slice
                                         .tentative_state_and_event_lower_bound(),
                                 );
+                                #[cfg(feature = "metrics")]
+                                tracing::debug!(?time, "transpose rolling back");
+
                                 return TrySourcePoll::Ok(SourcePoll::Interrupt {
                                     time,
                                     interrupt: Interrupt::Rollback,
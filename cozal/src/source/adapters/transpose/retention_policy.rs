@@ -0,0 +1,86 @@
+use std::num::NonZeroUsize;
+
+use crate::transposer::Transposer;
+
+/// Determines which saturated steps a [`Transpose`](super::Transpose) keeps around as snapshots.
+///
+/// Every saturated step holds a full copy of the transposer, so retaining all of them makes memory grow with the
+/// number of steps until the poll lower bound advances. Steps which are not retained are desaturated (they keep
+/// their inputs, but drop the transposer), and are recomputed from the nearest retained snapshot when a rollback or
+/// an interpolation needs them again. This trades CPU for memory.
+///
+/// Regardless of the policy, the earliest step and the latest saturated step are always retained, since they are
+/// needed to make progress.
+pub enum RetentionPolicy<T: Transposer> {
+    /// Retain every saturated step.
+    KeepAll,
+
+    /// Retain every nth step.
+    ///
+    /// This keeps the distance to the nearest snapshot (and therefore the amount of recomputation needed on
+    /// rollback) bounded by n.
+    EveryNth(NonZeroUsize),
+
+    /// Retain steps at or after the time computed from the latest saturated step's time.
+    ///
+    /// The function receives the time of the latest saturated step, and returns the earliest time that should still
+    /// be retained. Rollbacks before this horizon recompute from the earliest step.
+    Horizon(Box<dyn Fn(T::Time) -> T::Time>),
+
+    /// Retain at most this many saturated steps.
+    ///
+    /// When the cap is exceeded, steps are discarded so that the remaining snapshots stay roughly evenly spaced.
+    MaxCount(NonZeroUsize),
+}
+
+impl<T: Transposer> RetentionPolicy<T> {
+    /// Create a [`Horizon`](RetentionPolicy::Horizon) policy from a function mapping the latest saturated time
+    /// to the earliest retained time.
+    pub fn horizon<F>(horizon_fn: F) -> Self
+    where
+        F: Fn(T::Time) -> T::Time + 'static,
+    {
+        Self::Horizon(Box::new(horizon_fn))
+    }
+
+    /// Choose which of the candidate steps should be discarded.
+    ///
+    /// `candidates` are `(uuid, time)` pairs for every saturated step which is eligible to be discarded,
+    /// in timeline order. `retained_count` is the total number of saturated steps, including the ineligible ones,
+    /// and `latest_time` is the time of the latest saturated step.
+    ///
+    /// Returns the uuids of the steps to discard.
+    pub(crate) fn select_discards(
+        &self,
+        candidates: &[(u64, T::Time)],
+        retained_count: usize,
+        latest_time: T::Time,
+    ) -> Vec<u64> {
+        match self {
+            RetentionPolicy::KeepAll => Vec::new(),
+            RetentionPolicy::EveryNth(n) => candidates
+                .iter()
+                .filter(|(uuid, _)| uuid % n.get() as u64 != 0)
+                .map(|(uuid, _)| *uuid)
+                .collect(),
+            RetentionPolicy::Horizon(horizon_fn) => {
+                let horizon = horizon_fn(latest_time);
+                candidates
+                    .iter()
+                    .filter(|(_, time)| *time < horizon)
+                    .map(|(uuid, _)| *uuid)
+                    .collect()
+            }
+            RetentionPolicy::MaxCount(max) => {
+                let excess = retained_count.saturating_sub(max.get());
+
+                // prefer discarding steps with fewer trailing zeroes, so the remaining
+                // snapshots stay roughly evenly spaced.
+                let mut candidates: Vec<u64> = candidates.iter().map(|(uuid, _)| *uuid).collect();
+                candidates.sort_by_key(|uuid| (uuid.trailing_zeros(), *uuid));
+                candidates.truncate(excess);
+                candidates
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use archery::ArcTK;
+
+use crate::{
+    source::source_poll::{Interrupt, SourcePollErr},
+    transposer::{Transposer, step::BoxedInput},
+};
+
+type InputInterrupt<T> = (
+    <T as Transposer>::Time,
+    Interrupt<BoxedInput<'static, T, ArcTK>>,
+);
+
+/// What a [`Transpose`](super::Transpose) does with an input interrupt which is older than its rollback horizon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LateInputPolicy {
+    /// Discard the interrupt entirely.
+    Drop,
+
+    /// Move the interrupt forward to the horizon, so it is handled as late as possible without rolling back further.
+    Clamp,
+
+    /// Report the interrupt as a [`SourcePollErr::SpecificError`] from `poll_interrupts`.
+    Error,
+}
+
+/// Counters for the input interrupts which arrived older than the rollback horizon.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LateInputMetrics {
+    /// The number of late interrupts which were discarded.
+    pub dropped: usize,
+
+    /// The number of late interrupts which were moved forward to the horizon.
+    pub clamped: usize,
+
+    /// The number of late interrupts which were reported as errors.
+    pub rejected: usize,
+}
+
+/// A limit on how far back input interrupts may roll back a [`Transpose`](super::Transpose).
+pub struct RollbackHorizon<T: Transposer> {
+    window: Box<dyn Fn(T::Time) -> T::Time>,
+    policy: LateInputPolicy,
+    metrics: LateInputMetrics,
+}
+
+impl<T: Transposer> RollbackHorizon<T> {
+    /// Create a new rollback horizon.
+    ///
+    /// `window` receives the time of the latest step the transposer has computed, and returns the earliest time an
+    /// input interrupt may still roll back to.
+    pub fn new<F>(window: F, policy: LateInputPolicy) -> Self
+    where
+        F: Fn(T::Time) -> T::Time + 'static,
+    {
+        Self {
+            window: Box::new(window),
+            policy,
+            metrics: LateInputMetrics::default(),
+        }
+    }
+
+    /// The counts of late interrupts seen so far.
+    pub fn metrics(&self) -> LateInputMetrics {
+        self.metrics
+    }
+
+    /// Apply the late input policy to an interrupt.
+    ///
+    /// `frontier` is the time of the latest step the transposer has computed, if any.
+    ///
+    /// Returns the interrupt to handle (possibly moved to a later time), or `None` if it should be ignored.
+    pub(crate) fn screen(
+        &mut self,
+        frontier: Option<T::Time>,
+        time: T::Time,
+        interrupt: Interrupt<BoxedInput<'static, T, ArcTK>>,
+    ) -> Result<Option<InputInterrupt<T>>, SourcePollErr> {
+        let horizon = match frontier {
+            Some(frontier) => (self.window)(frontier),
+            None => return Ok(Some((time, interrupt))),
+        };
+
+        if time >= horizon {
+            return Ok(Some((time, interrupt)));
+        }
+
+        match self.policy {
+            LateInputPolicy::Drop => {
+                self.metrics.dropped += 1;
+                Ok(None)
+            }
+            LateInputPolicy::Clamp => {
+                self.metrics.clamped += 1;
+                let interrupt = match interrupt {
+                    Interrupt::Event(mut input) => {
+                        input.set_time(horizon);
+                        Interrupt::Event(input)
+                    }
+                    Interrupt::Rollback => Interrupt::Rollback,
+                };
+                Ok(Some((horizon, interrupt)))
+            }
+            LateInputPolicy::Error => {
+                self.metrics.rejected += 1;
+                Err(SourcePollErr::SpecificError(anyhow::anyhow!(
+                    "input interrupt at {:?} is older than the rollback horizon at {:?}",
+                    time,
+                    horizon
+                )))
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::{num::NonZeroUsize, task::Waker, time::Duration};
+
+use futures_test::future::FutureTestExt;
+
+use crate::{
+    source::{
+        adapters::transpose::{
+            RetentionPolicy, TransposeMetrics,
+            working_timeline_slice::{WorkingTimelineSlice, WorkingTimelineSlicePoll},
+        },
+        source_poll::{Interrupt, UpperBound},
+    },
+    transposer::{Transposer, step::PreInitStep},
+};
+
+#[derive(Clone, Debug)]
+struct CollatzTransposer {
+    current_value: u64,
+}
+
+impl Transposer for CollatzTransposer {
+    type Time = Duration;
+
+    type OutputEvent = u64;
+
+    type OutputState = u64;
+
+    type Scheduled = ();
+
+    fn prepare_to_init(&mut self) -> bool {
+        true
+    }
+
+    async fn init(&mut self, cx: &mut crate::transposer::InitContext<'_, Self>) {
+        cx.schedule_event(Duration::from_secs(0), ());
+    }
+
+    async fn handle_scheduled_event(
+        &mut self,
+        _: Self::Scheduled,
+        cx: &mut crate::transposer::HandleScheduleContext<'_, Self>,
+    ) {
+        async move {
+            cx.emit_event(self.current_value).await;
+            if self.current_value != 1 {
+                cx.schedule_event(cx.current_time() + Duration::from_secs(1), ())
+                    .unwrap();
+            }
+            if self.current_value.is_multiple_of(2) {
+                self.current_value /= 2;
+            } else {
+                self.current_value = self.current_value * 3 + 1;
+            }
+        }
+        .pending_once()
+        .await
+    }
+
+    async fn interpolate(
+        &self,
+        _cx: &mut crate::transposer::InterpolateContext<'_, Self>,
+    ) -> Self::OutputState {
+        self.current_value
+    }
+}
+
+fn new_slice(
+    retention_policy: RetentionPolicy<CollatzTransposer>,
+) -> WorkingTimelineSlice<CollatzTransposer> {
+    let mut slice = WorkingTimelineSlice::new(
+        CollatzTransposer { current_value: 70 },
+        PreInitStep::new(),
+        [69; 32],
+        retention_policy,
+    )
+    .unwrap();
+    slice.advance_interrupt_upper_bound(UpperBound::max());
+    slice
+}
+
+fn poll_to_completion(slice: &mut WorkingTimelineSlice<CollatzTransposer>) -> Vec<(Duration, u64)> {
+    let mut emitted = Vec::new();
+    loop {
+        match slice.poll(|_| Waker::noop().clone()) {
+            WorkingTimelineSlicePoll::Emitted { time, event } => emitted.push((time, event)),
+            WorkingTimelineSlicePoll::Ready { next_time: None } => break emitted,
+            WorkingTimelineSlicePoll::Pending { .. } => {}
+            _ => panic!(),
+        }
+    }
+}
+
+#[test]
+fn saturation_is_counted() {
+    let mut slice = new_slice(RetentionPolicy::KeepAll);
+    poll_to_completion(&mut slice);
+
+    assert_eq!(
+        slice.metrics(),
+        TransposeMetrics {
+            steps_saturated: 15,
+            events_emitted: 15,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn rollback_and_resaturation_are_counted() {
+    let mut slice = new_slice(RetentionPolicy::EveryNth(NonZeroUsize::new(4).unwrap()));
+    poll_to_completion(&mut slice);
+
+    let metrics = slice.metrics();
+    assert_eq!(metrics.steps_desaturated, 10);
+    assert_eq!(metrics.rollbacks, 0);
+
+    slice.handle_interrupt(0, Duration::from_secs(10), Interrupt::Rollback);
+    poll_to_completion(&mut slice);
+
+    let metrics = slice.metrics();
+    assert_eq!(metrics.rollbacks, 1);
+    assert_eq!(metrics.steps_rolled_back, 5);
+    // steps 9 and 10 are recomputed from the snapshot at 8 seconds before the rolled back steps.
+    assert_eq!(metrics.steps_resaturated, 2);
+    assert_eq!(metrics.events_emitted, 20);
+}
@@ -1,3 +1,9 @@
 mod no_input;
 // mod state_only_input;
 mod basic_happy_path;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "parallel")]
+mod parallel_resaturation;
+mod retention_policy;
+mod rollback_horizon;
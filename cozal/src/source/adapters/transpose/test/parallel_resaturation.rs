@@ -0,0 +1,46 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use super::retention_policy::{new_slice, poll_to_completion};
+use crate::source::adapters::transpose::RetentionPolicy;
+
+#[test]
+fn resaturates_gaps_from_different_snapshots() {
+    let mut reference = new_slice(RetentionPolicy::KeepAll);
+    poll_to_completion(&mut reference);
+
+    let mut slice = new_slice(RetentionPolicy::EveryNth(NonZeroUsize::new(4).unwrap()));
+    poll_to_completion(&mut slice);
+    assert_eq!(slice.retained_snapshot_count(), 5);
+
+    // three runs, from the snapshots at 0, 3 and 7 seconds.
+    let times = [1.5, 5.5, 6.5, 9.5].map(Duration::from_secs_f32);
+    for time in times {
+        assert!(slice.interpolate(time).is_err());
+    }
+
+    slice.resaturate_in_parallel(times);
+
+    // only the steps that were asked for are kept, alongside the existing snapshots.
+    assert_eq!(slice.retained_snapshot_count(), 9);
+    for time in times {
+        let interpolation = slice.interpolate(time).unwrap();
+        let expected = reference.interpolate(time).unwrap();
+        assert_eq!(
+            futures::executor::block_on(interpolation),
+            futures::executor::block_on(expected)
+        );
+    }
+}
+
+#[test]
+fn steps_between_requested_ones_are_discarded() {
+    let mut slice = new_slice(RetentionPolicy::EveryNth(NonZeroUsize::new(4).unwrap()));
+    poll_to_completion(&mut slice);
+
+    slice.resaturate_in_parallel([Duration::from_secs_f32(2.5)]);
+
+    // the steps at 1 and 2 seconds, after the first snapshot at 0.
+    assert_eq!(slice.retained_snapshot_count(), 6);
+    assert!(slice.interpolate(Duration::from_secs_f32(1.5)).is_err());
+    assert!(slice.interpolate(Duration::from_secs_f32(2.5)).is_ok());
+}
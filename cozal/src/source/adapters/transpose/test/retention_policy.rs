@@ -0,0 +1,179 @@
+use std::{num::NonZeroUsize, task::Waker, time::Duration};
+
+use futures_test::future::FutureTestExt;
+
+use crate::{
+    source::{
+        adapters::transpose::{
+            RetentionPolicy,
+            working_timeline_slice::{WorkingTimelineSlice, WorkingTimelineSlicePoll},
+        },
+        source_poll::{Interrupt, UpperBound},
+    },
+    transposer::{Transposer, step::PreInitStep},
+};
+
+#[derive(Clone, Debug)]
+pub(super) struct CollatzTransposer {
+    current_value: u64,
+}
+
+impl Transposer for CollatzTransposer {
+    type Time = Duration;
+
+    type OutputEvent = u64;
+
+    type OutputState = u64;
+
+    type Scheduled = ();
+
+    fn prepare_to_init(&mut self) -> bool {
+        true
+    }
+
+    async fn init(&mut self, cx: &mut crate::transposer::InitContext<'_, Self>) {
+        cx.schedule_event(Duration::from_secs(0), ());
+    }
+
+    async fn handle_scheduled_event(
+        &mut self,
+        _: Self::Scheduled,
+        cx: &mut crate::transposer::HandleScheduleContext<'_, Self>,
+    ) {
+        async move {
+            cx.emit_event(self.current_value).await;
+            if self.current_value != 1 {
+                cx.schedule_event(cx.current_time() + Duration::from_secs(1), ())
+                    .unwrap();
+            }
+            if self.current_value % 2 == 0 {
+                self.current_value /= 2;
+            } else {
+                self.current_value = self.current_value * 3 + 1;
+            }
+        }
+        .pending_once()
+        .await
+    }
+
+    async fn interpolate(
+        &self,
+        _cx: &mut crate::transposer::InterpolateContext<'_, Self>,
+    ) -> Self::OutputState {
+        self.current_value
+    }
+}
+
+pub(super) fn new_slice(
+    retention_policy: RetentionPolicy<CollatzTransposer>,
+) -> WorkingTimelineSlice<CollatzTransposer> {
+    let mut slice = WorkingTimelineSlice::new(
+        CollatzTransposer { current_value: 70 },
+        PreInitStep::new(),
+        [69; 32],
+        retention_policy,
+    )
+    .unwrap();
+    slice.advance_interrupt_upper_bound(UpperBound::max());
+    slice
+}
+
+pub(super) fn poll_to_completion(
+    slice: &mut WorkingTimelineSlice<CollatzTransposer>,
+) -> Vec<(Duration, u64)> {
+    let mut emitted = Vec::new();
+    loop {
+        match slice.poll(|_| Waker::noop().clone()) {
+            WorkingTimelineSlicePoll::Emitted { time, event } => emitted.push((time, event)),
+            WorkingTimelineSlicePoll::Ready { next_time: None } => break emitted,
+            WorkingTimelineSlicePoll::Pending { .. } => {}
+            _ => panic!(),
+        }
+    }
+}
+
+#[test]
+fn keep_all_retains_every_step() {
+    let mut slice = new_slice(RetentionPolicy::KeepAll);
+    let emitted = poll_to_completion(&mut slice);
+
+    assert_eq!(emitted.len(), 15);
+    assert_eq!(slice.retained_snapshot_count(), 15);
+}
+
+#[test]
+fn every_nth_discards_intermediate_steps() {
+    let mut slice = new_slice(RetentionPolicy::EveryNth(NonZeroUsize::new(4).unwrap()));
+    let emitted = poll_to_completion(&mut slice);
+
+    assert_eq!(emitted.len(), 15);
+    // the first, the last, and steps 4, 8, and 12.
+    assert_eq!(slice.retained_snapshot_count(), 5);
+}
+
+#[test]
+fn max_count_caps_snapshots() {
+    let mut slice = new_slice(RetentionPolicy::MaxCount(NonZeroUsize::new(4).unwrap()));
+    let emitted = poll_to_completion(&mut slice);
+
+    assert_eq!(emitted.len(), 15);
+    assert_eq!(slice.retained_snapshot_count(), 4);
+}
+
+#[test]
+fn horizon_discards_old_steps() {
+    let mut slice = new_slice(RetentionPolicy::horizon(|latest: Duration| {
+        latest.saturating_sub(Duration::from_secs(3))
+    }));
+    poll_to_completion(&mut slice);
+
+    // the first, and the steps at 11, 12, 13 and 14 seconds.
+    assert_eq!(slice.retained_snapshot_count(), 5);
+}
+
+#[test]
+fn rollback_recomputes_from_nearest_snapshot() {
+    let mut reference = new_slice(RetentionPolicy::KeepAll);
+    let reference_emitted = poll_to_completion(&mut reference);
+
+    let mut slice = new_slice(RetentionPolicy::EveryNth(NonZeroUsize::new(4).unwrap()));
+    poll_to_completion(&mut slice);
+
+    let rollback_time = Duration::from_secs(10);
+    assert_eq!(
+        slice.handle_interrupt(0, rollback_time, Interrupt::Rollback),
+        Some(rollback_time)
+    );
+
+    // the discarded steps before the rollback are recomputed silently,
+    // and only the rolled back events are emitted again.
+    let emitted = poll_to_completion(&mut slice);
+    let expected: Vec<_> = reference_emitted
+        .into_iter()
+        .filter(|(t, _)| *t >= rollback_time)
+        .collect();
+    assert_eq!(emitted, expected);
+}
+
+#[test]
+fn interpolate_discarded_step() {
+    let mut slice = new_slice(RetentionPolicy::EveryNth(NonZeroUsize::new(4).unwrap()));
+    poll_to_completion(&mut slice);
+
+    let time = Duration::from_secs_f32(5.5);
+    assert!(slice.interpolate(time).is_err());
+
+    loop {
+        match slice.poll_snapshot(time, |_| Waker::noop().clone()) {
+            WorkingTimelineSlicePoll::Ready { next_time } => {
+                assert_eq!(next_time, Some(Duration::from_secs(6)));
+                break;
+            }
+            WorkingTimelineSlicePoll::Pending { .. } => {}
+            _ => panic!(),
+        }
+    }
+
+    let interpolation = slice.interpolate(time).unwrap();
+    assert_eq!(futures::executor::block_on(interpolation), 40);
+}
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use crate::{
+    source::{
+        adapters::transpose::{LateInputMetrics, LateInputPolicy, RollbackHorizon},
+        source_poll::Interrupt,
+    },
+    transposer::{Transposer, TransposerInput, TransposerInputEventHandler, step::BoxedInput},
+};
+
+#[derive(Clone, Debug, Default)]
+struct KeyPressCounter {
+    presses: u64,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Debug)]
+struct KeyPressInput;
+
+impl Transposer for KeyPressCounter {
+    type Time = Duration;
+
+    type OutputEvent = ();
+
+    type OutputState = u64;
+
+    type Scheduled = ();
+
+    fn prepare_to_init(&mut self) -> bool {
+        true
+    }
+
+    async fn init(&mut self, _cx: &mut crate::transposer::InitContext<'_, Self>) {}
+
+    async fn handle_scheduled_event(
+        &mut self,
+        _: Self::Scheduled,
+        _cx: &mut crate::transposer::HandleScheduleContext<'_, Self>,
+    ) {
+    }
+
+    async fn interpolate(
+        &self,
+        _cx: &mut crate::transposer::InterpolateContext<'_, Self>,
+    ) -> Self::OutputState {
+        self.presses
+    }
+}
+
+impl TransposerInput for KeyPressInput {
+    type Base = KeyPressCounter;
+
+    type InputEvent = ();
+
+    type InputState = ();
+
+    const SORT: u64 = 0;
+}
+
+impl TransposerInputEventHandler<KeyPressInput> for KeyPressCounter {
+    fn register_input(&mut self, _input: KeyPressInput) -> bool {
+        true
+    }
+
+    async fn handle_input_event(
+        &mut self,
+        _input: &KeyPressInput,
+        _event: &(),
+        _cx: &mut crate::transposer::HandleInputContext<'_, Self>,
+    ) {
+        self.presses += 1;
+    }
+}
+
+fn key_press(secs: u64) -> Interrupt<BoxedInput<'static, KeyPressCounter>> {
+    Interrupt::Event(BoxedInput::new(
+        Duration::from_secs(secs),
+        KeyPressInput,
+        (),
+    ))
+}
+
+fn two_second_window(policy: LateInputPolicy) -> RollbackHorizon<KeyPressCounter> {
+    RollbackHorizon::new(
+        |frontier: Duration| frontier.saturating_sub(Duration::from_secs(2)),
+        policy,
+    )
+}
+
+#[test]
+fn inputs_within_horizon_are_untouched() {
+    let mut horizon = two_second_window(LateInputPolicy::Error);
+    let frontier = Some(Duration::from_secs(10));
+
+    let (time, _) = horizon
+        .screen(frontier, Duration::from_secs(8), key_press(8))
+        .unwrap()
+        .unwrap();
+    assert_eq!(time, Duration::from_secs(8));
+
+    // nothing has been computed yet, so nothing is late.
+    assert!(
+        horizon
+            .screen(None, Duration::from_secs(0), key_press(0))
+            .unwrap()
+            .is_some()
+    );
+
+    assert_eq!(horizon.metrics(), LateInputMetrics::default());
+}
+
+#[test]
+fn late_inputs_are_dropped() {
+    let mut horizon = two_second_window(LateInputPolicy::Drop);
+    let frontier = Some(Duration::from_secs(10));
+
+    assert!(
+        horizon
+            .screen(frontier, Duration::from_secs(7), key_press(7))
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        horizon
+            .screen(frontier, Duration::from_secs(3), Interrupt::Rollback)
+            .unwrap()
+            .is_none()
+    );
+
+    assert_eq!(horizon.metrics().dropped, 2);
+}
+
+#[test]
+fn late_inputs_are_clamped() {
+    let mut horizon = two_second_window(LateInputPolicy::Clamp);
+    let frontier = Some(Duration::from_secs(10));
+
+    let (time, interrupt) = horizon
+        .screen(frontier, Duration::from_secs(7), key_press(7))
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(time, Duration::from_secs(8));
+    match interrupt {
+        Interrupt::Event(input) => assert_eq!(input.get_time(), Duration::from_secs(8)),
+        Interrupt::Rollback => panic!(),
+    }
+
+    assert_eq!(horizon.metrics().clamped, 1);
+}
+
+#[test]
+fn late_inputs_are_rejected() {
+    let mut horizon = two_second_window(LateInputPolicy::Error);
+    let frontier = Some(Duration::from_secs(10));
+
+    assert!(
+        horizon
+            .screen(frontier, Duration::from_secs(1), key_press(1))
+            .is_err()
+    );
+
+    assert_eq!(horizon.metrics().rejected, 1);
+}
@@ -1,10 +1,26 @@
 use std::{
+    cmp::Ordering,
     collections::{BTreeSet, VecDeque},
+    ops::ControlFlow,
     task::Waker,
 };
+#[cfg(feature = "parallel")]
+use std::{
+    sync::{
+        Arc,
+        atomic::{self, AtomicBool},
+    },
+    task::Wake,
+};
 
 use archery::ArcTK;
+#[cfg(feature = "parallel")]
+use archery::SharedPointer;
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+#[cfg(feature = "parallel")]
+use crate::transposer::step::WrappedTransposer;
 use crate::{
     source::source_poll::{Interrupt, LowerBound, UpperBound},
     transposer::{
@@ -16,12 +32,20 @@ use crate::{
     },
 };
 
+#[cfg(feature = "metrics")]
+use super::metrics::TransposeMetrics;
+use super::retention_policy::RetentionPolicy;
+
 pub struct WorkingTimelineSlice<T: Transposer + 'static> {
     init_step: Option<Box<InitStep<T, ArcTK>>>,
     steps: VecDeque<StepWrapper<T>>,
     next_step_uuid: u64,
     num_deleted_steps: usize,
     input_buffer: BTreeSet<BoxedInput<'static, T, ArcTK>>,
+    retention_policy: RetentionPolicy<T>,
+
+    #[cfg(feature = "metrics")]
+    metrics: TransposeMetrics,
 
     poll_lower_bound: LowerBound<T::Time>,
     interrupt_lower_bound: LowerBound<T::Time>,
@@ -30,6 +54,14 @@ pub struct WorkingTimelineSlice<T: Transposer + 'static> {
 
 impl<T: Transposer + Clone> WorkingTimelineSlice<T> {}
 
+#[cfg(feature = "metrics")]
+impl<T: Transposer> WorkingTimelineSlice<T> {
+    /// the summary of the work done so far.
+    pub fn metrics(&self) -> TransposeMetrics {
+        self.metrics
+    }
+}
+
 struct StepWrapper<T: Transposer + 'static> {
     uuid: u64,
     step: Step<'static, T, ArcTK>,
@@ -41,6 +73,7 @@ impl<T: Transposer + Clone> WorkingTimelineSlice<T> {
         transposer: T,
         pre_init_step: PreInitStep<T>,
         rng_seed: [u8; 32],
+        retention_policy: RetentionPolicy<T>,
     ) -> Result<Self, T> {
         Ok(Self {
             init_step: Some(Box::new(InitStep::new(
@@ -52,6 +85,10 @@ impl<T: Transposer + Clone> WorkingTimelineSlice<T> {
             next_step_uuid: 1,
             num_deleted_steps: 0,
             input_buffer: BTreeSet::new(),
+            retention_policy,
+
+            #[cfg(feature = "metrics")]
+            metrics: TransposeMetrics::default(),
 
             poll_lower_bound: LowerBound::min(),
             interrupt_lower_bound: LowerBound::min(),
@@ -64,53 +101,100 @@ impl<T: Transposer + Clone> WorkingTimelineSlice<T> {
             return;
         }
 
-        let first_included_index = self
+        // the last step before the bound is still needed to interpolate times after the bound.
+        let last_needed_index = match self
             .steps
-            .partition_point(|s| new_min.test(&s.step.get_time()));
+            .partition_point(|s| !new_min.test(&s.step.get_time()))
+            .checked_sub(1)
+        {
+            Some(i) => i,
+            None => return,
+        };
 
-        let last_deleted_index = match first_included_index.checked_sub(1) {
+        // and if it isn't retained, it will have to be recomputed from the nearest snapshot before it.
+        let first_kept_index = match (0..=last_needed_index)
+            .rev()
+            .find(|i| self.steps[*i].step.is_saturated())
+        {
             Some(i) => i,
             None => return,
         };
 
+        if first_kept_index == 0 {
+            return;
+        }
+
         self.init_step = None;
-        self.num_deleted_steps += self.steps.drain(..=last_deleted_index).count();
+        self.num_deleted_steps += self.steps.drain(..first_kept_index).count();
     }
 
-    /// get the top uuid, and the previous, both mutably.
-    /// errors when there aren't any non-init steps.
-    fn get_last_two_steps(
+    /// get the step at `position` and the one before it, both mutably.
+    ///
+    /// position 0 is the init step, and position i is `steps[i - 1]`.
+    fn get_step_and_prev_mut(
         &mut self,
-    ) -> Result<
-        (
-            &mut dyn PossiblyInitStep<'static, T, ArcTK>,
-            &mut StepWrapper<T>,
-        ),
+        position: usize,
+    ) -> (
         &mut dyn PossiblyInitStep<'static, T, ArcTK>,
-    > {
-        match self.steps.len() {
-            0 => Err(&mut **self.init_step.as_mut().unwrap()),
-            1 => Ok((
+        &mut StepWrapper<T>,
+    ) {
+        match position {
+            0 => unreachable!(),
+            1 => (
                 &mut **self.init_step.as_mut().unwrap(),
-                self.steps.back_mut().unwrap(),
-            )),
+                self.steps.front_mut().unwrap(),
+            ),
             _ => {
-                let (a, b) = self.steps.as_mut_slices();
-
-                let (a, b) = match b.len() {
-                    0 => a.split_at_mut(a.len() - 1),
-                    1 => (a, b),
-                    _ => b.split_at_mut(b.len() - 1),
+                // this is all a dance to get a mutable reference to
+                // steps[i] and steps[i - 1] simultaneously with no unsafe
+                let i = position - 1;
+                let (front, back) = self.steps.as_mut_slices();
+
+                let (before, after) = match i.cmp(&front.len()) {
+                    Ordering::Less => front.split_at_mut(i),
+                    Ordering::Equal => (front, back),
+                    Ordering::Greater => back.split_at_mut(i - front.len()),
                 };
 
-                Ok((&mut a.last_mut().unwrap().step, b.last_mut().unwrap()))
+                (
+                    &mut before.last_mut().unwrap().step,
+                    after.first_mut().unwrap(),
+                )
             }
         }
     }
 
-    /// get the uuid of the last step.
-    fn top_uuid(&self) -> u64 {
-        self.steps.back().map(|s| s.uuid).unwrap_or(0)
+    fn get_step_at_position_mut(
+        &mut self,
+        position: usize,
+    ) -> &mut dyn PossiblyInitStep<'static, T, ArcTK> {
+        match position {
+            0 => &mut **self.init_step.as_mut().unwrap(),
+            i => &mut self.steps[i - 1].step,
+        }
+    }
+
+    fn uuid_at_position(&self, position: usize) -> u64 {
+        match position {
+            0 => 0,
+            i => self.steps[i - 1].uuid,
+        }
+    }
+
+    /// the position of the latest saturated step at or before `position`, if any.
+    fn nearest_saturated_position(&self, position: usize) -> Option<usize> {
+        if let Some(i) = self
+            .steps
+            .range(..position)
+            .rposition(|s| s.step.is_saturated())
+        {
+            return Some(i + 1);
+        }
+
+        match &self.init_step {
+            Some(init) if init.is_saturated() => Some(0),
+            _ => None,
+        }
     }
 
     /// Advance the lower bound that interpolates may be created by.
@@ -136,13 +220,36 @@ impl<T: Transposer + Clone> WorkingTimelineSlice<T> {
     }
 
     /// process the given interrupt.
+    ///
+    /// returns the time events must be rolled back to, if any emitted events were invalidated.
     pub fn handle_interrupt(
         &mut self,
         input_hash: u64,
         time: T::Time,
         interrupt: Interrupt<BoxedInput<'static, T, ArcTK>>,
     ) -> Option<T::Time> {
-        let first_delete = self.steps.partition_point(|s| time <= s.step.get_time());
+        let first_delete = self.steps.partition_point(|s| s.step.get_time() < time);
+
+        let rolled_back = self
+            .steps
+            .range(first_delete..)
+            .any(|s| s.step.has_produced_events());
+
+        #[cfg(feature = "metrics")]
+        {
+            let steps_rolled_back = self.steps.len() - first_delete;
+            if steps_rolled_back > 0 {
+                self.metrics.rollbacks += 1;
+                self.metrics.steps_rolled_back += steps_rolled_back;
+            }
+            tracing::debug!(
+                ?time,
+                input_hash,
+                steps_rolled_back,
+                rolled_back,
+                "transpose handled input interrupt"
+            );
+        }
 
         match interrupt {
             Interrupt::Event(e) => {
@@ -154,6 +261,8 @@ impl<T: Transposer + Clone> WorkingTimelineSlice<T> {
                 );
             }
             Interrupt::Rollback => {
+                self.input_buffer
+                    .retain(|i| i.get_input_hash() != input_hash || i.get_time() < time);
                 self.input_buffer.extend(
                     self.steps
                         .drain(first_delete..)
@@ -163,7 +272,7 @@ impl<T: Transposer + Clone> WorkingTimelineSlice<T> {
             }
         }
 
-        todo!()
+        rolled_back.then_some(time)
     }
 
     /// poll work on the steps, which is only complete when the next step is after the
@@ -174,81 +283,194 @@ impl<T: Transposer + Clone> WorkingTimelineSlice<T> {
     {
         // assume the top step is never saturated unless there are no remaining events.
         loop {
-            let top_step_uuid = self.top_uuid();
+            let top_position = self.steps.len();
+
+            if let Some(top) = self.steps.back()
+                && top.step.is_unsaturated()
+                && !self.interrupt_upper_bound.test(&top.step.get_time())
+            {
+                return WorkingTimelineSlicePoll::Ready {
+                    next_time: Some(top.step.get_time()),
+                };
+            }
+
+            // usually this is the top step, but if the retention policy discarded steps which are
+            // now needed again, it is the first one after the nearest snapshot.
+            let working_position = match self.nearest_saturated_position(top_position) {
+                Some(p) if p == top_position => {
+                    let top_step: &dyn PossiblyInitStep<_, _> = match self.steps.back() {
+                        Some(s) => &s.step,
+                        None => &**self.init_step.as_ref().unwrap(),
+                    };
+                    let step = match top_step.next_unsaturated(&mut self.input_buffer).unwrap() {
+                        Some(step) => step,
+                        None => return WorkingTimelineSlicePoll::Ready { next_time: None },
+                    };
+                    self.steps.push_back(StepWrapper {
+                        uuid: self.next_step_uuid,
+                        step,
+                    });
+                    self.next_step_uuid += 1;
 
-            let top_step: &mut dyn PossiblyInitStep<_, _> = match self.steps.back_mut() {
-                Some(s) => &mut s.step,
-                None => &mut **self.init_step.as_mut().unwrap(),
+                    continue;
+                }
+                Some(p) => p + 1,
+                None => 0,
             };
 
-            if top_step.is_unsaturated() {
-                if !self.interrupt_upper_bound.test(&top_step.get_time()) {
-                    return WorkingTimelineSlicePoll::Ready {
-                        next_time: Some(top_step.get_time()),
-                    };
+            if let ControlFlow::Break(poll) =
+                self.poll_position(working_position, &mut interrupt_waker_fn)
+            {
+                return poll;
+            }
+        }
+    }
+
+    /// poll work on recomputing the step needed to interpolate `time`, if the retention policy discarded it.
+    ///
+    /// Once this returns `Ready`, `interpolate` may be called for `time`.
+    pub fn poll_snapshot<F>(
+        &mut self,
+        time: T::Time,
+        mut interrupt_waker_fn: F,
+    ) -> WorkingTimelineSlicePoll<T>
+    where
+        F: FnMut(u64) -> Waker,
+    {
+        let target_position = self.steps.partition_point(|s| s.step.get_time() <= time);
+        let next_time = self.steps.get(target_position).map(|s| s.step.get_time());
+
+        loop {
+            let working_position = match self.nearest_saturated_position(target_position) {
+                Some(p) if p == target_position => {
+                    return WorkingTimelineSlicePoll::Ready { next_time };
                 }
+                Some(p) => p + 1,
+                None => 0,
+            };
 
-                let (prev, curr) = match self.get_last_two_steps() {
-                    Ok(x) => x,
-                    Err(_) => unreachable!(),
-                };
+            if let ControlFlow::Break(poll) =
+                self.poll_position(working_position, &mut interrupt_waker_fn)
+            {
+                return poll;
+            }
+        }
+    }
+
+    /// make one unit of progress on saturating the step at `position`,
+    /// which must be unsaturated or saturating, and preceded by a saturated step.
+    fn poll_position<F>(
+        &mut self,
+        position: usize,
+        interrupt_waker_fn: &mut F,
+    ) -> ControlFlow<WorkingTimelineSlicePoll<T>>
+    where
+        F: FnMut(u64) -> Waker,
+    {
+        let step_uuid = self.uuid_at_position(position);
+        let step = self.get_step_at_position_mut(position);
+
+        if step.is_unsaturated() {
+            let (prev, curr) = self.get_step_and_prev_mut(position);
 
-                curr.step.start_saturate_clone(prev).unwrap();
+            // a step which has been fully saturated before can no longer produce events.
+            #[cfg(feature = "metrics")]
+            let resaturating = !curr.step.can_produce_events();
 
-                continue;
+            curr.step.start_saturate_clone(prev).unwrap();
+
+            #[cfg(feature = "metrics")]
+            {
+                self.metrics.steps_saturated += 1;
+                if resaturating {
+                    self.metrics.steps_resaturated += 1;
+                }
             }
 
-            if top_step.is_saturating() {
-                let top_step_waker = interrupt_waker_fn(top_step_uuid);
-                match top_step.poll(&top_step_waker).unwrap() {
-                    StepPoll::Emitted(event) => {
-                        return WorkingTimelineSlicePoll::Emitted {
-                            time: top_step.get_time(),
-                            event,
-                        };
-                    }
-                    StepPoll::StateRequested(input) => {
-                        return WorkingTimelineSlicePoll::StateRequested {
-                            time: top_step.get_time(),
-                            input,
-                            step_uuid: self.top_uuid(),
-                        };
-                    }
-                    StepPoll::Pending => {
-                        return WorkingTimelineSlicePoll::Pending {
-                            step_uuid: self.top_uuid(),
-                        };
-                    }
-                    StepPoll::Ready => {}
+            return ControlFlow::Continue(());
+        }
+
+        let waker = interrupt_waker_fn(step_uuid);
+        match step.poll(&waker).unwrap() {
+            StepPoll::Emitted(event) => {
+                let time = step.get_time();
+
+                #[cfg(feature = "metrics")]
+                {
+                    self.metrics.events_emitted += 1;
                 }
 
-                continue;
+                ControlFlow::Break(WorkingTimelineSlicePoll::Emitted { time, event })
+            }
+            StepPoll::StateRequested(input) => {
+                ControlFlow::Break(WorkingTimelineSlicePoll::StateRequested {
+                    time: step.get_time(),
+                    input,
+                    step_uuid,
+                })
+            }
+            StepPoll::Pending => {
+                ControlFlow::Break(WorkingTimelineSlicePoll::Pending { step_uuid })
+            }
+            StepPoll::Ready => {
+                self.apply_retention_policy(step_uuid);
+                ControlFlow::Continue(())
             }
+        }
+    }
 
-            if top_step.is_saturated() {
-                let step = match top_step.next_unsaturated(&mut self.input_buffer).unwrap() {
-                    Some(step) => step,
-                    None => return WorkingTimelineSlicePoll::Ready { next_time: None },
-                };
-                self.steps.push_back(StepWrapper {
-                    uuid: self.next_step_uuid,
-                    step,
-                });
-                self.next_step_uuid += 1;
+    /// desaturate any steps the retention policy no longer wants to keep.
+    ///
+    /// `just_saturated_uuid` is always kept, since the steps after it are computed from it.
+    fn apply_retention_policy(&mut self, just_saturated_uuid: u64) {
+        if let RetentionPolicy::KeepAll = self.retention_policy {
+            return;
+        }
+
+        let latest_saturated_index = match self.steps.iter().rposition(|s| s.step.is_saturated()) {
+            Some(i) => i,
+            None => return,
+        };
+
+        let latest_time = self.steps[latest_saturated_index].step.get_time();
+
+        // the earliest step and the latest saturated step are always kept.
+        let candidates: Vec<_> = self
+            .steps
+            .range(1..latest_saturated_index.max(1))
+            .filter(|s| s.uuid != just_saturated_uuid && s.step.is_saturated())
+            .map(|s| (s.uuid, s.step.get_time()))
+            .collect();
+
+        let retained_count = self.retained_snapshot_count();
 
-                continue;
+        let discards =
+            self.retention_policy
+                .select_discards(&candidates, retained_count, latest_time);
+
+        for uuid in discards {
+            if let Ok(i) = self.steps.binary_search_by_key(&uuid, |s| s.uuid) {
+                self.steps[i].step.desaturate();
+
+                #[cfg(feature = "metrics")]
+                {
+                    self.metrics.steps_desaturated += 1;
+                }
             }
         }
     }
 
+    /// the number of saturated steps currently retained as snapshots.
+    pub fn retained_snapshot_count(&self) -> usize {
+        self.steps.iter().filter(|s| s.step.is_saturated()).count()
+    }
+
     /// provide a requested input state to the requesting step.
     pub fn provide_input_state(
         &mut self,
         uuid: u64,
         state: Box<ErasedInputState<T>>,
     ) -> Result<(), Box<ErasedInputState<T>>> {
-        use std::cmp::Ordering;
-
         for step in self.steps.iter_mut().rev() {
             match step.uuid.cmp(&uuid) {
                 Ordering::Less => return Err(state),
@@ -288,6 +510,15 @@ impl<T: Transposer + Clone> WorkingTimelineSlice<T> {
         }
     }
 
+    /// the time of the latest step which has been computed, if any.
+    pub fn frontier_time(&self) -> Option<T::Time> {
+        self.steps
+            .iter()
+            .rev()
+            .find(|s| !s.step.is_unsaturated())
+            .map(|s| s.step.get_time())
+    }
+
     pub fn get_time(&self, uuid: u64) -> Option<T::Time> {
         match uuid {
             0 => None,
@@ -299,6 +530,175 @@ impl<T: Transposer + Clone> WorkingTimelineSlice<T> {
     }
 }
 
+#[cfg(feature = "parallel")]
+impl<T> WorkingTimelineSlice<T>
+where
+    T: Transposer + Clone + Send + Sync,
+    T::Time: Send + Sync,
+    T::Scheduled: Send + Sync,
+{
+    /// recompute the steps needed to interpolate each of `times`, spreading the runs which start from
+    /// different snapshots across the rayon thread pool.
+    ///
+    /// only the snapshots' transposers are sent to other threads. each run is recomputed by copies of its steps
+    /// which never leave the thread that made them, so the transposer's handler futures don't need to be `Send`.
+    ///
+    /// only steps which handle nothing but scheduled events are recomputed this way, so no input events
+    /// cross threads. anything after the first step that isn't, or that requests an input state, is left
+    /// for `poll_snapshot` to recompute serially.
+    pub fn resaturate_in_parallel(&mut self, times: impl IntoIterator<Item = T::Time>) {
+        let mut target_positions: Vec<usize> = times
+            .into_iter()
+            .map(|time| self.steps.partition_point(|s| s.step.get_time() <= time))
+            .collect();
+        target_positions.sort_unstable();
+        target_positions.dedup();
+
+        // (snapshot index, last index to saturate, indices to keep saturated), all indices into `steps`.
+        let mut runs: Vec<(usize, usize, Vec<usize>)> = Vec::new();
+        for target_position in target_positions {
+            // runs only start from snapshots in `steps`, not the init step.
+            let snapshot_index = match self.nearest_saturated_position(target_position) {
+                Some(p) if p != 0 && p != target_position => p - 1,
+                _ => continue,
+            };
+
+            let last_index = match (snapshot_index + 1..target_position)
+                .take_while(|i| {
+                    let step = &self.steps[*i].step;
+                    step.is_unsaturated() && !step.can_produce_events() && step.has_only_scheduled()
+                })
+                .last()
+            {
+                Some(i) => i,
+                None => continue,
+            };
+
+            match runs.last_mut() {
+                Some((s, last, keep)) if *s == snapshot_index => {
+                    *last = last_index;
+                    keep.push(last_index);
+                }
+                _ => runs.push((snapshot_index, last_index, vec![last_index])),
+            }
+        }
+
+        if runs.is_empty() {
+            return;
+        }
+
+        let keep_all = matches!(self.retention_policy, RetentionPolicy::KeepAll);
+        let jobs: Vec<_> = runs
+            .iter()
+            .map(|(snapshot_index, last_index, keep)| ResaturationRun {
+                snapshot: self.steps[*snapshot_index].step.clone().unwrap(),
+                steps: (snapshot_index + 1..=*last_index)
+                    .map(|i| (self.steps[i].step.get_time(), keep_all || keep.contains(&i)))
+                    .collect(),
+            })
+            .collect();
+
+        let results: Vec<_> = jobs
+            .into_par_iter()
+            .map(ResaturationRun::resaturate)
+            .collect();
+
+        #[cfg(feature = "metrics")]
+        let mut saturated = 0;
+        #[cfg(feature = "metrics")]
+        let mut desaturated = 0;
+
+        for ((snapshot_index, _, _), finished) in runs.into_iter().zip(results) {
+            #[cfg(feature = "metrics")]
+            {
+                saturated += finished.len();
+            }
+
+            for (i, wrapped_transposer) in finished.into_iter().enumerate() {
+                match wrapped_transposer {
+                    Some(wrapped_transposer) => {
+                        let step = &mut self.steps[snapshot_index + 1 + i].step;
+                        step.saturate_with(wrapped_transposer)
+                            .expect("only unsaturated steps are recomputed");
+                    }
+                    #[cfg(feature = "metrics")]
+                    None => desaturated += 1,
+                    #[cfg(not(feature = "metrics"))]
+                    None => {}
+                }
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.steps_saturated += saturated;
+            self.metrics.steps_resaturated += saturated;
+            self.metrics.steps_desaturated += desaturated;
+            tracing::debug!(saturated, desaturated, "transpose resaturated in parallel");
+        }
+    }
+}
+
+/// a snapshot's transposer, and the times of the steps after it to recompute from it on another thread.
+#[cfg(feature = "parallel")]
+struct ResaturationRun<T: Transposer + 'static> {
+    snapshot: SharedPointer<WrappedTransposer<T, ArcTK>, ArcTK>,
+
+    // the time of each step, and whether it stays saturated.
+    steps: Vec<(T::Time, bool)>,
+}
+
+#[cfg(feature = "parallel")]
+impl<T: Transposer + Clone> ResaturationRun<T> {
+    /// recompute the steps in order, stopping at the first one which can't finish on its own.
+    ///
+    /// returns the finished transposer of each step that was recomputed, if it stays saturated.
+    fn resaturate(self) -> Vec<Option<SharedPointer<WrappedTransposer<T, ArcTK>, ArcTK>>> {
+        let mut finished = Vec::with_capacity(self.steps.len());
+        let mut prev = self.snapshot;
+
+        for (time, keep) in self.steps {
+            let mut step = Step::<'static, T, ArcTK>::new_scheduled_saturating(time, prev)
+                .expect("a new step is unsaturated");
+
+            if !poll_until_saturated(&mut step) {
+                break;
+            }
+
+            prev = step.take().expect("the step was just saturated");
+            finished.push(keep.then(|| SharedPointer::clone(&prev)));
+        }
+
+        finished
+    }
+}
+
+/// poll a saturating step until it is saturated, dropping the events it emits again.
+///
+/// returns false if it is waiting on something only the caller can provide, like an input state.
+#[cfg(feature = "parallel")]
+fn poll_until_saturated<T: Transposer + Clone>(step: &mut Step<'static, T, ArcTK>) -> bool {
+    struct WokenFlag(AtomicBool);
+
+    impl Wake for WokenFlag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, atomic::Ordering::Relaxed);
+        }
+    }
+
+    let woken = Arc::new(WokenFlag(AtomicBool::new(false)));
+    let waker = Waker::from(woken.clone());
+
+    loop {
+        match step.poll(&waker).expect("the step is saturating") {
+            StepPoll::Ready => return true,
+            StepPoll::Emitted(_) => {}
+            StepPoll::Pending if woken.0.swap(false, atomic::Ordering::Relaxed) => {}
+            StepPoll::Pending | StepPoll::StateRequested(_) => return false,
+        }
+    }
+}
+
 pub enum WorkingTimelineSlicePoll<T: Transposer> {
     Emitted {
         time: T::Time,
@@ -40,6 +40,10 @@ struct InterpolateInnerInProgress<T: Transposer, P: SharedPointerKind> {
     future: MaybeUninit<interpolate::FutureImpl<T, P>>,
     input_state: InputStateManager<T>,
 
+    // when the first poll happened, for reporting how long the interpolation took.
+    #[cfg(feature = "metrics")]
+    started_at: std::time::Instant,
+
     // future contains a reference to input_state.
     _pin: PhantomPinned,
 }
@@ -119,6 +123,8 @@ impl<T: Transposer, P: SharedPointerKind> Future for Interpolation<T, P> {
                     interpolation_time,
                     future: MaybeUninit::uninit(),
                     input_state,
+                    #[cfg(feature = "metrics")]
+                    started_at: std::time::Instant::now(),
                     _pin: PhantomPinned,
                 });
 
@@ -152,7 +158,24 @@ impl<T: Transposer, P: SharedPointerKind> Future for Interpolation<T, P> {
         let future = unsafe { future.assume_init_mut() };
         let pin = unsafe { Pin::new_unchecked(future) };
 
-        pin.poll(cx)
+        let poll = pin.poll(cx);
+
+        #[cfg(feature = "metrics")]
+        if poll.is_ready()
+            && let InterpolationInner::InProgress(InterpolateInnerInProgress {
+                interpolation_time,
+                started_at,
+                ..
+            }) = &unpinned.inner
+        {
+            tracing::debug!(
+                time = ?interpolation_time,
+                elapsed = ?started_at.elapsed(),
+                "interpolation complete"
+            );
+        }
+
+        poll
     }
 }
 
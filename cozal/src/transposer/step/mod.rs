@@ -24,7 +24,7 @@ pub use interpolation::Interpolation;
 pub use pre_init_step::PreInitStep;
 pub use step::{InterpolateErr, NextUnsaturatedErr, PollErr, SaturateErr, Step, StepPoll};
 pub use sub_step::boxed_input::BoxedInput;
-use wrapped_transposer::WrappedTransposer;
+pub(crate) use wrapped_transposer::WrappedTransposer;
 
 use super::Transposer;
 
@@ -205,6 +205,9 @@ impl<'a, T: Transposer + 'a, P: SharedPointerKind + 'a> Step<'a, T, P> {
             return Err(SaturateErr::IncorrectPrevious);
         }
 
+        #[cfg(feature = "metrics")]
+        tracing::trace!(time = ?self.time, "step start saturate (take)");
+
         self.start_saturate(prev.take()?)
     }
 
@@ -229,6 +232,9 @@ impl<'a, T: Transposer + 'a, P: SharedPointerKind + 'a> Step<'a, T, P> {
             return Err(SaturateErr::IncorrectPrevious);
         }
 
+        #[cfg(feature = "metrics")]
+        tracing::trace!(time = ?self.time, "step start saturate (clone)");
+
         self.start_saturate(prev.clone()?)
     }
 
@@ -299,6 +305,61 @@ impl<'a, T: Transposer + 'a, P: SharedPointerKind + 'a> Step<'a, T, P> {
     pub fn has_produced_events(&self) -> bool {
         self.event_count != 0
     }
+
+    /// true if the step only handles scheduled events, and has no inputs.
+    pub fn has_only_scheduled(&self) -> bool {
+        self.steps.iter().all(|step| step.as_ref().is_scheduled())
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<'a, T: Transposer + Clone + 'a, P: SharedPointerKind + 'a> Step<'a, T, P> {
+    /// A saturating step which handles the scheduled events at `time`, starting from `wrapped_transposer`.
+    ///
+    /// This is used to recompute a step away from the timeline it belongs to. It doesn't know which events the
+    /// original step has emitted, so it emits all of them again.
+    pub(crate) fn new_scheduled_saturating(
+        time: T::Time,
+        wrapped_transposer: SharedPointer<WrappedTransposer<T, P>, P>,
+    ) -> Result<Self, SaturateErr> {
+        let mut step = Self {
+            sequence_number: 1,
+            steps: vec![ScheduledSubStep::new_boxed(time)],
+            status: StepStatus::Unsaturated,
+            time,
+            shared_step_state: Self::new_shared_step_state(),
+            event_count: 0,
+            can_produce_events: true,
+            #[cfg(debug_assertions)]
+            uuid_self: uuid::Uuid::new_v4(),
+            #[cfg(debug_assertions)]
+            uuid_prev: uuid::Uuid::nil(),
+        };
+        step.start_saturate(wrapped_transposer)?;
+        Ok(step)
+    }
+
+    /// Saturate the step with the transposer a copy of it finished with, from `new_scheduled_saturating`.
+    ///
+    /// # Errors
+    ///
+    /// - If the current step is not Unsaturated.
+    /// - If the step handles anything but scheduled events.
+    pub(crate) fn saturate_with(
+        &mut self,
+        wrapped_transposer: SharedPointer<WrappedTransposer<T, P>, P>,
+    ) -> Result<(), SaturateErr> {
+        if !self.is_unsaturated() || !self.has_only_scheduled() {
+            return Err(SaturateErr::SelfNotUnsaturated);
+        }
+
+        // all the sub steps are scheduled, and only the last one holds the finished transposer.
+        let last = self.steps.last_mut().unwrap();
+        *last = BoxedSubStep::new(Box::new(ScheduledSubStep::Saturated { wrapped_transposer }));
+        self.status = StepStatus::Saturated;
+        self.can_produce_events = false;
+        Ok(())
+    }
 }
 
 impl<'a, T: Transposer + Clone + 'a, P: SharedPointerKind + 'a> PossiblyInitStep<'a, T, P>
@@ -479,6 +540,9 @@ impl<'a, T: Transposer + Clone + 'a, P: SharedPointerKind + 'a> PossiblyInitStep
     }
 
     fn desaturate(&mut self) {
+        #[cfg(feature = "metrics")]
+        tracing::trace!(time = ?self.time, "step desaturate");
+
         match self.get_step_status_mut() {
             ActiveStepStatusMut::Saturated(step) => step.as_mut().desaturate(),
             ActiveStepStatusMut::Saturating(step) => step.as_mut().desaturate(),
@@ -89,6 +89,13 @@ impl<'t, T: Transposer + 't, P: SharedPointerKind + 't> BoxedInput<'t, T, P> {
         self.0.as_ref().get_time()
     }
 
+    /// Move the input to a different time.
+    ///
+    /// Boxed inputs are always unsaturated, so this cannot fail.
+    pub fn set_time(&mut self, time: T::Time) {
+        self.0.as_mut().set_time(time).unwrap()
+    }
+
     /// Get the type id of the input.
     pub fn get_input_type_id(&self) -> TypeId {
         self.0.as_ref().input_sort().unwrap().1
@@ -10,7 +10,7 @@ use std::{
 
 use archery::{SharedPointer, SharedPointerKind};
 
-use super::{BoxedSubStep, INPUT_SUB_STEP_SORT_PHASE, StartSaturateErr, SubStep};
+use super::{BoxedSubStep, INPUT_SUB_STEP_SORT_PHASE, SetTimeErr, StartSaturateErr, SubStep};
 use crate::transposer::{
     Transposer, TransposerInput, TransposerInputEventHandler,
     input_erasure::{HasErasedInputExt, HasInput},
@@ -292,6 +292,18 @@ where
         this.status = InputSubStepStatus::Unsaturated { time };
     }
 
+    fn set_time(self: Pin<&mut Self>, new_time: T::Time) -> Result<(), SetTimeErr> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        match &mut this.status {
+            InputSubStepStatus::Unsaturated { time } => {
+                *time = new_time;
+                Ok(())
+            }
+            _ => Err(SetTimeErr::NotUnsaturated),
+        }
+    }
+
     fn sort_phase(&self) -> usize {
         INPUT_SUB_STEP_SORT_PHASE
     }
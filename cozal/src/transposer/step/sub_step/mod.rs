@@ -126,6 +126,17 @@ pub unsafe trait SubStep<T: Transposer, P: SharedPointerKind> {
     ) -> Option<SharedPointer<WrappedTransposer<T, P>, P>>;
 
     fn desaturate(self: Pin<&mut Self>);
+
+    fn set_time(self: Pin<&mut Self>, time: T::Time) -> Result<(), SetTimeErr> {
+        let _ = time;
+        Err(SetTimeErr::NotInput)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SetTimeErr {
+    NotInput,
+    NotUnsaturated,
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]